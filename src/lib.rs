@@ -0,0 +1,1266 @@
+//! Streaming upload body that reports progress as bytes are read.
+//!
+//! `TrackableBodyStream` wraps a slice of a local file and feeds it to the
+//! AWS SDK as a [`ByteStream`], invoking a callback once the body is built so
+//! callers can drive an `indicatif` progress bar (or anything else) without
+//! the SDK needing to know progress tracking exists. The callback is not
+//! invoked again as individual chunks are read or sent.
+
+use aws_sdk_s3::{
+    operation::create_multipart_upload::CreateMultipartUploadOutput,
+    types::{CompletedMultipartUpload, CompletedPart, StorageClass},
+    Client as S3Client,
+};
+use aws_smithy_http::byte_stream::{ByteStream, Length};
+use bytes::Bytes;
+use futures::{stream, Stream, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use rand::Rng;
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Content type, storage class, and user metadata for an upload. Anything
+/// left unset falls back to a sensible default: `content_type` is sniffed
+/// from the file extension, `storage_class` is left to S3's own default
+/// (`STANDARD`), and `metadata` is empty.
+///
+/// Shared by every upload path in the crate that creates an object — the
+/// file-based [`upload_file`] and [`upload_file_concurrent`] (including their
+/// single-part `put_object` path) and [`TrackableBodyStream::from_stream`] —
+/// so options set by a caller apply no matter which path is taken.
+#[derive(Debug, Clone, Default)]
+pub struct UploadOptions {
+    pub content_type: Option<String>,
+    pub storage_class: Option<StorageClass>,
+    pub metadata: HashMap<String, String>,
+}
+
+impl UploadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    pub fn storage_class(mut self, storage_class: StorageClass) -> Self {
+        self.storage_class = Some(storage_class);
+        self
+    }
+
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// The content type to send, falling back to sniffing `key`'s
+    /// extension when none was set explicitly.
+    pub fn resolved_content_type(&self, key: &str) -> Option<String> {
+        self.content_type
+            .clone()
+            .or_else(|| sniff_content_type(key))
+    }
+}
+
+/// Guesses a MIME type from a file extension so uploads without an
+/// explicit content type don't all land as `application/octet-stream`.
+fn sniff_content_type(key: &str) -> Option<String> {
+    let extension = Path::new(key).extension()?.to_str()?.to_lowercase();
+    let mime_type = match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "json" => "application/json",
+        "txt" => "text/plain",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" => "application/javascript",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        _ => return None,
+    };
+    Some(mime_type.to_string())
+}
+
+#[cfg(test)]
+mod upload_options_tests {
+    use super::sniff_content_type;
+
+    #[test]
+    fn sniffs_known_extensions() {
+        assert_eq!(sniff_content_type("a.jpg").as_deref(), Some("image/jpeg"));
+        assert_eq!(sniff_content_type("a.JPEG").as_deref(), Some("image/jpeg"));
+        assert_eq!(sniff_content_type("a.json").as_deref(), Some("application/json"));
+        assert_eq!(sniff_content_type("archive.tar.gz").as_deref(), None);
+    }
+
+    #[test]
+    fn falls_back_to_none_for_unknown_or_missing_extensions() {
+        assert_eq!(sniff_content_type("no_extension"), None);
+        assert_eq!(sniff_content_type("file.xyz"), None);
+    }
+}
+
+/// Called as `(total_size, bytes_sent_so_far, bytes_in_this_chunk)`.
+pub type ProgressCallback = Box<dyn FnMut(u64, u64, u64) + Send>;
+
+/// A file-backed body for a single multipart upload part (or a whole small
+/// object) that reports how much of itself has been read.
+pub struct TrackableBodyStream {
+    path: PathBuf,
+    chunk_size: usize,
+    offset: u64,
+    length: u64,
+    callback: Option<ProgressCallback>,
+}
+
+impl TrackableBodyStream {
+    /// Size of the reads issued against the underlying file. Defaults to
+    /// the whole `length` in one read.
+    pub fn chunk_size(&mut self, chunk_size: usize) -> &mut Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Byte offset into the file this stream starts reading from.
+    pub fn offset(&mut self, offset: u64) -> &mut Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Number of bytes this stream will yield, starting at `offset`.
+    pub fn length(&mut self, length: u64) -> &mut Self {
+        self.length = length;
+        self
+    }
+
+    /// Number of bytes this stream will yield.
+    pub fn content_length(&self) -> u64 {
+        self.length
+    }
+
+    /// Register a callback invoked once, when [`Self::to_multipart_s3_stream`]
+    /// builds the body, as `(total_size, bytes_sent_so_far, bytes_in_this_chunk)`
+    /// = `(length, offset + length, length)`; it is not called per chunk read.
+    pub fn set_callback<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: FnMut(u64, u64, u64) + Send + 'static,
+    {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Build the `ByteStream` the AWS SDK actually sends, wiring the
+    /// progress callback up via `ByteStream`'s reader and firing it once
+    /// the read is staged (the SDK streams the body lazily, so this marks
+    /// the chunk as queued, not necessarily flushed over the wire).
+    pub async fn to_multipart_s3_stream(&mut self) -> Result<ByteStream, std::io::Error> {
+        let stream = ByteStream::read_from()
+            .path(&self.path)
+            .offset(self.offset)
+            .length(Length::Exact(self.length))
+            .buffer_size(self.chunk_size)
+            .build()
+            .await
+            .map_err(std::io::Error::other)?;
+
+        if let Some(callback) = self.callback.as_mut() {
+            callback(self.length, self.offset + self.length, self.length);
+        }
+
+        Ok(stream)
+    }
+}
+
+impl TryFrom<PathBuf> for TrackableBodyStream {
+    type Error = std::io::Error;
+
+    fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
+        if !path.exists() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} does not exist", path.display()),
+            ));
+        }
+
+        let length = std::fs::metadata(&path)?.len();
+
+        Ok(Self {
+            path,
+            chunk_size: length as usize,
+            offset: 0,
+            length,
+            callback: None,
+        })
+    }
+}
+
+/// S3's allowed multipart part size range; `from_stream` clamps whatever
+/// part size it's given into this window.
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+const MAX_PART_SIZE: usize = 5 * 1024 * 1024 * 1024;
+
+/// Error produced while driving [`TrackableBodyStream::from_stream`].
+#[derive(Debug)]
+pub enum StreamUploadError {
+    CreateMultipartUpload(aws_sdk_s3::Error),
+    UploadPart(aws_sdk_s3::Error),
+    CompleteMultipartUpload(aws_sdk_s3::Error),
+    AbortMultipartUpload(aws_sdk_s3::Error),
+    Source(Box<dyn std::error::Error + Send + Sync>),
+    EmptyStream,
+}
+
+impl std::fmt::Display for StreamUploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CreateMultipartUpload(e) => write!(f, "create_multipart_upload failed: {e}"),
+            Self::UploadPart(e) => write!(f, "upload_part failed: {e}"),
+            Self::CompleteMultipartUpload(e) => write!(f, "complete_multipart_upload failed: {e}"),
+            Self::AbortMultipartUpload(e) => write!(f, "abort_multipart_upload failed: {e}"),
+            Self::Source(e) => write!(f, "source stream failed: {e}"),
+            Self::EmptyStream => write!(f, "source stream yielded no bytes, nothing was uploaded"),
+        }
+    }
+}
+
+impl std::error::Error for StreamUploadError {}
+
+/// Default number of attempts a single part gets before the whole upload is
+/// aborted, for callers of [`TrackableBodyStream::from_stream`] that don't
+/// need a different retry budget.
+pub const DEFAULT_MAX_PART_ATTEMPTS: u32 = 3;
+
+/// Exponential backoff with jitter between retries: `200ms * 2^attempt`
+/// (the exponent is capped at 6) plus up to 200ms of random jitter, so
+/// retrying parts don't all wake up in lockstep. Shared by every retry loop
+/// in the crate, including the file-based upload paths.
+pub async fn backoff(attempt: u32) {
+    let base = std::time::Duration::from_millis(200);
+    let exponential = base * 2u32.pow(attempt.min(6));
+    let jitter = std::time::Duration::from_millis(
+        rand::thread_rng().gen_range(0..base.as_millis() as u64),
+    );
+    tokio::time::sleep(exponential + jitter).await;
+}
+
+/// `Init` before the first chunk arrives (no upload_id exists yet),
+/// `Uploading` once parts are being accumulated and sent, `Complete` once
+/// `complete_multipart_upload` has been called.
+enum StreamUploadState {
+    Init {
+        client: S3Client,
+        bucket: String,
+        key: String,
+        part_size: usize,
+        options: UploadOptions,
+        max_attempts: u32,
+    },
+    Uploading {
+        client: S3Client,
+        bucket: String,
+        key: String,
+        upload_id: String,
+        part_size: usize,
+        part_number: i32,
+        parts: Vec<CompletedPart>,
+        buffer: Vec<u8>,
+        max_attempts: u32,
+    },
+    Complete,
+}
+
+impl StreamUploadState {
+    async fn push(self, chunk: Bytes) -> Result<Self, StreamUploadError> {
+        let mut state = match self {
+            Self::Init {
+                client,
+                bucket,
+                key,
+                part_size,
+                options,
+                max_attempts,
+            } => {
+                let created = client
+                    .create_multipart_upload()
+                    .bucket(&bucket)
+                    .key(&key)
+                    .set_content_type(options.resolved_content_type(&key))
+                    .set_storage_class(options.storage_class.clone())
+                    .set_metadata(Some(options.metadata.clone()))
+                    .send()
+                    .await
+                    .map_err(|e| StreamUploadError::CreateMultipartUpload(e.into()))?;
+                let upload_id = created.upload_id().unwrap_or_default().to_string();
+                Self::Uploading {
+                    client,
+                    bucket,
+                    key,
+                    upload_id,
+                    part_size,
+                    part_number: 1,
+                    parts: Vec::new(),
+                    buffer: Vec::new(),
+                    max_attempts,
+                }
+            }
+            uploading => uploading,
+        };
+
+        if let Self::Uploading { buffer, .. } = &mut state {
+            buffer.extend_from_slice(&chunk);
+        }
+
+        state.flush_if_full().await
+    }
+
+    async fn flush_if_full(self) -> Result<Self, StreamUploadError> {
+        match &self {
+            Self::Uploading {
+                part_size, buffer, ..
+            } if buffer.len() >= *part_size => self.flush_part().await,
+            _ => Ok(self),
+        }
+    }
+
+    async fn flush_part(self) -> Result<Self, StreamUploadError> {
+        match self {
+            Self::Uploading {
+                client,
+                bucket,
+                key,
+                upload_id,
+                part_size,
+                part_number,
+                mut parts,
+                buffer,
+                max_attempts,
+            } => {
+                let mut attempt = 0;
+                let uploaded = loop {
+                    match client
+                        .upload_part()
+                        .bucket(&bucket)
+                        .key(&key)
+                        .upload_id(&upload_id)
+                        .part_number(part_number)
+                        .body(ByteStream::from(buffer.clone()))
+                        .send()
+                        .await
+                    {
+                        Ok(res) => break res,
+                        Err(err) if attempt + 1 < max_attempts => {
+                            attempt += 1;
+                            eprintln!(
+                                "part {part_number} failed (attempt {attempt}/{max_attempts}), retrying: {err}"
+                            );
+                            backoff(attempt).await;
+                        }
+                        Err(err) => {
+                            client
+                                .abort_multipart_upload()
+                                .bucket(&bucket)
+                                .key(&key)
+                                .upload_id(&upload_id)
+                                .send()
+                                .await
+                                .map_err(|e| StreamUploadError::AbortMultipartUpload(e.into()))?;
+                            return Err(StreamUploadError::UploadPart(err.into()));
+                        }
+                    }
+                };
+                parts.push(
+                    CompletedPart::builder()
+                        .e_tag(uploaded.e_tag.unwrap_or_default())
+                        .part_number(part_number)
+                        .build(),
+                );
+                Ok(Self::Uploading {
+                    client,
+                    bucket,
+                    key,
+                    upload_id,
+                    part_size,
+                    part_number: part_number + 1,
+                    parts,
+                    buffer: Vec::new(),
+                    max_attempts,
+                })
+            }
+            other => Ok(other),
+        }
+    }
+
+    async fn finish(self) -> Result<(), StreamUploadError> {
+        let state = match self {
+            Self::Uploading { ref buffer, .. } if !buffer.is_empty() => self.flush_part().await?,
+            other => other,
+        };
+
+        match state {
+            Self::Uploading {
+                client,
+                bucket,
+                key,
+                upload_id,
+                parts,
+                ..
+            } => {
+                let completed_upload = CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build();
+                client
+                    .complete_multipart_upload()
+                    .bucket(&bucket)
+                    .key(&key)
+                    .upload_id(&upload_id)
+                    .multipart_upload(completed_upload)
+                    .send()
+                    .await
+                    .map_err(|e| StreamUploadError::CompleteMultipartUpload(e.into()))?;
+                Ok(())
+            }
+            // The source stream never yielded a single byte, so no multipart upload
+            // was ever created; surface that distinctly instead of reporting success
+            // for a file that was never written.
+            Self::Init { .. } => Err(StreamUploadError::EmptyStream),
+            Self::Complete => Ok(()),
+        }
+    }
+}
+
+impl TrackableBodyStream {
+    /// Upload an arbitrary async byte stream — a pipe, an HTTP body,
+    /// compression output, anything of unknown length that never touches
+    /// the local filesystem — as a multipart object.
+    ///
+    /// `part_size` is clamped into S3's allowed `5 MB..=5 GB` part range.
+    /// Incoming bytes are buffered until the buffer reaches `part_size`,
+    /// at which point they're flushed as the next part; whatever remains
+    /// when the stream ends is flushed as the final part.
+    ///
+    /// `options` carries the same content-type/storage-class/metadata
+    /// settings as the file-based upload paths.
+    ///
+    /// Each part gets up to `max_attempts` tries with exponential backoff
+    /// before the whole upload is aborted, matching the retry behaviour of
+    /// the file-based multipart paths.
+    ///
+    /// Returns [`StreamUploadError::EmptyStream`] if `stream` never yields a
+    /// byte — no multipart upload is created in that case, so callers can
+    /// tell "nothing was uploaded" apart from a genuine success.
+    pub async fn from_stream<S, E>(
+        client: &S3Client,
+        bucket: &str,
+        key: &str,
+        part_size: usize,
+        options: &UploadOptions,
+        max_attempts: u32,
+        stream: S,
+    ) -> Result<(), StreamUploadError>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let part_size = part_size.clamp(MIN_PART_SIZE, MAX_PART_SIZE);
+
+        let mut state = StreamUploadState::Init {
+            client: client.clone(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            part_size,
+            options: options.clone(),
+            max_attempts,
+        };
+
+        futures::pin_mut!(stream);
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| StreamUploadError::Source(Box::new(e)))?;
+            if chunk.is_empty() {
+                continue;
+            }
+            state = state.push(chunk).await?;
+        }
+
+        state.finish().await
+    }
+}
+
+/// Default chunk size for multipart uploads: 5 MB, S3's minimum allowed
+/// part size. Increase it to send larger chunks.
+pub const DEFAULT_CHUNK_SIZE: u64 = 1024 * 1024 * 5;
+/// S3's hard cap on the number of parts a multipart upload can have.
+pub const MAX_CHUNKS: u64 = 10000;
+/// At or below this, a single put_object is cheaper than the create/upload/complete
+/// dance, and multipart uploads aren't even allowed below the 5 MB minimum
+/// part size.
+pub const DEFAULT_MAX_SINGLE_PART_SIZE: u64 = 1024 * 1024 * 64;
+
+/// Splits `file_size` into `chunk_size`-sized chunks, returning
+/// `(chunk_count, size_of_last_chunk)`. The last chunk absorbs the
+/// remainder, so it's only ever equal to `chunk_size` when `file_size`
+/// divides evenly.
+pub fn chunk_layout(file_size: u64, chunk_size: u64) -> (u64, u64) {
+    let mut chunk_count = (file_size / chunk_size) + 1;
+    let mut size_of_last_chunk = file_size % chunk_size;
+    if size_of_last_chunk == 0 {
+        size_of_last_chunk = chunk_size;
+        chunk_count -= 1;
+    }
+    (chunk_count, size_of_last_chunk)
+}
+
+/// Surfaces which stage of an upload failed, with the `upload_id` attached
+/// for multipart failures so callers can decide whether to abort, resume,
+/// or retry instead of just getting a generic boxed error.
+#[derive(Debug)]
+pub enum UploadError {
+    Body(std::io::Error),
+    PutObject(aws_sdk_s3::Error),
+    CreateMultipartUpload(aws_sdk_s3::Error),
+    UploadPart {
+        upload_id: String,
+        part_number: i32,
+        source: aws_sdk_s3::Error,
+    },
+    CompleteMultipartUpload {
+        upload_id: String,
+        source: aws_sdk_s3::Error,
+    },
+    AbortMultipartUpload {
+        upload_id: String,
+        source: aws_sdk_s3::Error,
+    },
+    ListParts {
+        upload_id: String,
+        source: aws_sdk_s3::Error,
+    },
+    ListMultipartUploads(aws_sdk_s3::Error),
+    EmptyFile,
+    TooManyChunks { chunk_count: u64, max_chunks: u64 },
+    PartSizeMismatch {
+        upload_id: String,
+        part_number: i32,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl std::fmt::Display for UploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Body(e) => write!(f, "failed to read upload body: {e}"),
+            Self::PutObject(e) => write!(f, "put_object failed: {e}"),
+            Self::CreateMultipartUpload(e) => write!(f, "create_multipart_upload failed: {e}"),
+            Self::UploadPart {
+                upload_id,
+                part_number,
+                source,
+            } => write!(
+                f,
+                "upload_part {part_number} failed for upload {upload_id}: {source}"
+            ),
+            Self::CompleteMultipartUpload { upload_id, source } => write!(
+                f,
+                "complete_multipart_upload failed for upload {upload_id}: {source}"
+            ),
+            Self::AbortMultipartUpload { upload_id, source } => write!(
+                f,
+                "abort_multipart_upload failed for upload {upload_id}: {source}"
+            ),
+            Self::ListParts { upload_id, source } => {
+                write!(f, "list_parts failed for upload {upload_id}: {source}")
+            }
+            Self::ListMultipartUploads(e) => write!(f, "list_multipart_uploads failed: {e}"),
+            Self::EmptyFile => write!(f, "file is empty, nothing to upload"),
+            Self::TooManyChunks {
+                chunk_count,
+                max_chunks,
+            } => write!(
+                f,
+                "file needs {chunk_count} chunks, which is over the {max_chunks} limit; try increasing chunk size"
+            ),
+            Self::PartSizeMismatch {
+                upload_id,
+                part_number,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "part {part_number} of upload {upload_id} is {actual} bytes, but chunk_size implies {expected}; \
+                 pass the chunk_size the upload was created with to resume it safely"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+impl From<std::io::Error> for UploadError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Body(err)
+    }
+}
+
+/// Uploads one part, making up to `max_attempts` attempts in total with
+/// backoff between them before giving up and returning the last error.
+async fn upload_part_with_retry(
+    aws_client: &S3Client,
+    bucket_name: &str,
+    key: &str,
+    upload_id: &str,
+    part_number: i32,
+    path: &Path,
+    offset: u64,
+    length: u64,
+    max_attempts: u32,
+) -> Result<CompletedPart, UploadError> {
+    let mut attempt = 0;
+    loop {
+        let stream = ByteStream::read_from()
+            .path(path)
+            .offset(offset)
+            .length(Length::Exact(length))
+            .build()
+            .await
+            .map_err(std::io::Error::other)?;
+
+        match aws_client
+            .upload_part()
+            .key(key)
+            .bucket(bucket_name)
+            .upload_id(upload_id)
+            .body(stream)
+            .part_number(part_number)
+            .send()
+            .await
+        {
+            Ok(res) => {
+                return Ok(CompletedPart::builder()
+                    .e_tag(res.e_tag.unwrap_or_default())
+                    .part_number(part_number)
+                    .build());
+            }
+            Err(err) if attempt + 1 < max_attempts => {
+                attempt += 1;
+                eprintln!(
+                    "part {part_number} failed (attempt {attempt}/{max_attempts}), retrying: {err}"
+                );
+                backoff(attempt).await;
+            }
+            Err(err) => {
+                return Err(UploadError::UploadPart {
+                    upload_id: upload_id.to_string(),
+                    part_number,
+                    source: err.into(),
+                });
+            }
+        }
+    }
+}
+
+/// Aborts a multipart upload so S3 stops accruing storage charges for its
+/// orphaned parts.
+async fn abort_upload(
+    aws_client: &S3Client,
+    bucket_name: &str,
+    key: &str,
+    upload_id: &str,
+) -> Result<(), UploadError> {
+    aws_client
+        .abort_multipart_upload()
+        .bucket(bucket_name)
+        .key(key)
+        .upload_id(upload_id)
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|err| UploadError::AbortMultipartUpload {
+            upload_id: upload_id.to_string(),
+            source: err.into(),
+        })
+}
+
+/// Uploads a single small object with one `put_object` call instead of the
+/// multipart ceremony. The progress bar is set from the body's callback,
+/// which fires once when the body is built, so it moves to the full size
+/// before `put_object` is sent rather than advancing as bytes are read.
+async fn upload_file_single_part(
+    aws_client: &S3Client,
+    bucket_name: &str,
+    key: &str,
+    file_size: u64,
+    options: &UploadOptions,
+) -> Result<(), UploadError> {
+    println!("File is under the single-part threshold, using put_object.");
+
+    let pb = ProgressBar::new(file_size);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.white/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+        .unwrap()
+        .progress_chars("█  "));
+    pb.set_message(format!("Uploading {} to {}", key, bucket_name));
+
+    let mut body = TrackableBodyStream::try_from(PathBuf::from(key))?;
+    let pb_for_callback = pb.clone();
+    body.set_callback(move |_total_size, sent, _chunk| {
+        pb_for_callback.set_position(sent);
+    });
+    let stream = body.to_multipart_s3_stream().await?;
+
+    aws_client
+        .put_object()
+        .bucket(bucket_name)
+        .key(key)
+        .set_content_type(options.resolved_content_type(key))
+        .set_storage_class(options.storage_class.clone())
+        .set_metadata(Some(options.metadata.clone()))
+        .body(stream)
+        .send()
+        .await
+        .map_err(|err| UploadError::PutObject(err.into()))?;
+
+    pb.finish_with_message("Upload complete.");
+    println!("Done uploading file.");
+
+    Ok(())
+}
+
+pub async fn upload_file(
+    aws_client: &S3Client,
+    bucket_name: &str,
+    key: &str,
+    chunk_size: u64,
+    max_single_part_size: u64,
+    max_part_attempts: u32,
+    options: &UploadOptions,
+) -> Result<(), UploadError> {
+    let path = Path::new(&key);
+    let file_size = tokio::fs::metadata(path)
+        .await
+        .expect("it exists I swear")
+        .len();
+
+    if file_size == 0 {
+        return Err(UploadError::EmptyFile);
+    }
+
+    if file_size <= max_single_part_size {
+        return upload_file_single_part(aws_client, bucket_name, key, file_size, options).await;
+    }
+
+    let (chunk_count, size_of_last_chunk) = chunk_layout(file_size, chunk_size);
+
+    if chunk_count > MAX_CHUNKS {
+        return Err(UploadError::TooManyChunks {
+            chunk_count,
+            max_chunks: MAX_CHUNKS,
+        });
+    }
+
+    let multipart_upload_res: CreateMultipartUploadOutput = aws_client
+        .create_multipart_upload()
+        .bucket(bucket_name)
+        .key(key)
+        .set_content_type(options.resolved_content_type(key))
+        .set_storage_class(options.storage_class.clone())
+        .set_metadata(Some(options.metadata.clone()))
+        .send()
+        .await
+        .map_err(|err| UploadError::CreateMultipartUpload(err.into()))?;
+    // snippet-end:[rust.example_code.s3.create_multipart_upload]
+    let upload_id = multipart_upload_res.upload_id().unwrap();
+
+    // //Create a file of random characters for the upload.
+    // let mut file = File::create(&key).expect("Could not create sample file.");
+    // // Loop until the file is 5 chunks.
+    // let pb_local_write = ProgressBar::new(DEFAULT_CHUNK_SIZE * 4);
+    // pb_local_write.set_style(ProgressStyle::default_bar()
+    //     .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.white/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+    //     .unwrap()
+    //     .progress_chars("█  "));
+    // let msg_local_write = format!("Creating sample file.");
+    // pb_local_write.set_message(msg_local_write);
+    // while file.metadata().unwrap().len() <= DEFAULT_CHUNK_SIZE * 4 {
+    //     let rand_string: String = thread_rng()
+    //         .sample_iter(&Alphanumeric)
+    //         .take(256)
+    //         .map(char::from)
+    //         .collect();
+    //     let return_string: String = "\n".to_string();
+    //     file.write_all(rand_string.as_ref())
+    //         .expect("Error writing to file.");
+    //     pb_local_write.set_position(file.metadata().unwrap().len());
+    //     file.write_all(return_string.as_ref())
+    //         .expect("Error writing to file.");
+    //     pb_local_write.set_position(file.metadata().unwrap().len());
+    // }
+    // pb_local_write.finish_with_message("Done writing sample file.");
+    // let mut file = File::open(key).unwrap();
+
+    let mut upload_parts: Vec<CompletedPart> = Vec::new();
+
+    println!("Uploading {} chunks.", chunk_count);
+
+    let pb = ProgressBar::new(file_size);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.white/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+        .unwrap()
+        .progress_chars("█  "));
+    let msg = format!("Uploading {} to {}", key, bucket_name);
+    pb.set_message(msg);
+
+    for chunk_index in 0..chunk_count {
+        let this_chunk = if chunk_count - 1 == chunk_index {
+            size_of_last_chunk
+        } else {
+            chunk_size
+        };
+        let uploaded = chunk_index * chunk_size;
+        pb.set_message(format!(
+            "Uploading chunk {} of {}.",
+            chunk_index + 1,
+            chunk_count
+        ));
+        //Chunk index needs to start at 0, but part numbers start at 1.
+        let part_number = (chunk_index as i32) + 1;
+        let part = match upload_part_with_retry(
+            aws_client,
+            bucket_name,
+            key,
+            upload_id,
+            part_number,
+            path,
+            uploaded,
+            this_chunk,
+            max_part_attempts,
+        )
+        .await
+        {
+            Ok(part) => part,
+            Err(err) => {
+                pb.abandon_with_message("Upload failed, aborting.");
+                if let Err(abort_err) = abort_upload(aws_client, bucket_name, key, upload_id).await {
+                    eprintln!("failed to abort orphaned upload {upload_id}: {abort_err}");
+                }
+                return Err(err);
+            }
+        };
+        upload_parts.push(part);
+        pb.set_position(uploaded + this_chunk);
+    }
+    pb.finish_with_message("All chunks uploaded.");
+    let completed_multipart_upload: CompletedMultipartUpload = CompletedMultipartUpload::builder()
+        .set_parts(Some(upload_parts))
+        .build();
+    println!("Completing upload.");
+    aws_client
+        .complete_multipart_upload()
+        .bucket(bucket_name)
+        .key(key)
+        .multipart_upload(completed_multipart_upload)
+        .upload_id(upload_id)
+        .send()
+        .await
+        .map_err(|err| UploadError::CompleteMultipartUpload {
+            upload_id: upload_id.to_string(),
+            source: err.into(),
+        })?;
+    println!("Done uploading file.");
+
+    Ok(())
+}
+
+/// Like [`upload_file`], but drives up to `concurrency` part uploads at
+/// once instead of sending them one at a time. Like `upload_file`, files at
+/// or under `max_single_part_size` take the cheaper `put_object` path
+/// instead of the multipart dance.
+///
+/// Parts complete out of order, so the progress bar is incremented by each
+/// part's size as it finishes (`pb.inc`) rather than set to an absolute
+/// position, and the returned `CompletedPart`s are sorted by part number
+/// before `complete_multipart_upload` is called, since S3 requires parts
+/// in ascending order. If any part fails, the whole upload is aborted so
+/// no partial object is billed.
+pub async fn upload_file_concurrent(
+    aws_client: &S3Client,
+    bucket_name: &str,
+    key: &str,
+    chunk_size: u64,
+    max_single_part_size: u64,
+    concurrency: NonZeroUsize,
+    max_part_attempts: u32,
+    options: &UploadOptions,
+) -> Result<(), UploadError> {
+    let path = Path::new(&key);
+    let file_size = tokio::fs::metadata(path)
+        .await
+        .expect("it exists I swear")
+        .len();
+
+    if file_size == 0 {
+        return Err(UploadError::EmptyFile);
+    }
+
+    if file_size <= max_single_part_size {
+        return upload_file_single_part(aws_client, bucket_name, key, file_size, options).await;
+    }
+
+    let (chunk_count, size_of_last_chunk) = chunk_layout(file_size, chunk_size);
+
+    if chunk_count > MAX_CHUNKS {
+        return Err(UploadError::TooManyChunks {
+            chunk_count,
+            max_chunks: MAX_CHUNKS,
+        });
+    }
+
+    let multipart_upload_res: CreateMultipartUploadOutput = aws_client
+        .create_multipart_upload()
+        .bucket(bucket_name)
+        .key(key)
+        .set_content_type(options.resolved_content_type(key))
+        .set_storage_class(options.storage_class.clone())
+        .set_metadata(Some(options.metadata.clone()))
+        .send()
+        .await
+        .map_err(|err| UploadError::CreateMultipartUpload(err.into()))?;
+    let upload_id = multipart_upload_res.upload_id().unwrap().to_string();
+
+    println!(
+        "Uploading {} chunks with up to {} in flight.",
+        chunk_count, concurrency
+    );
+
+    let pb = Arc::new(ProgressBar::new(file_size));
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.white/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+        .unwrap()
+        .progress_chars("█  "));
+    pb.set_message(format!("Uploading {} to {}", key, bucket_name));
+
+    let part_results: Vec<Result<CompletedPart, UploadError>> = stream::iter(0..chunk_count)
+        .map(|chunk_index| {
+            let pb = Arc::clone(&pb);
+            let upload_id = upload_id.clone();
+            async move {
+                let this_chunk = if chunk_count - 1 == chunk_index {
+                    size_of_last_chunk
+                } else {
+                    chunk_size
+                };
+                let uploaded = chunk_index * chunk_size;
+                let part_number = (chunk_index as i32) + 1;
+
+                let part = upload_part_with_retry(
+                    aws_client,
+                    bucket_name,
+                    key,
+                    &upload_id,
+                    part_number,
+                    path,
+                    uploaded,
+                    this_chunk,
+                    max_part_attempts,
+                )
+                .await?;
+
+                pb.inc(this_chunk);
+
+                Ok(part)
+            }
+        })
+        .buffer_unordered(concurrency.get())
+        .collect()
+        .await;
+
+    let mut upload_parts: Vec<CompletedPart> = Vec::with_capacity(part_results.len());
+    for part_result in part_results {
+        match part_result {
+            Ok(part) => upload_parts.push(part),
+            Err(err) => {
+                pb.abandon_with_message("Upload failed, aborting.");
+                if let Err(abort_err) = abort_upload(aws_client, bucket_name, key, &upload_id).await {
+                    eprintln!("failed to abort orphaned upload {upload_id}: {abort_err}");
+                }
+                return Err(err);
+            }
+        }
+    }
+    upload_parts.sort_by_key(|part| part.part_number());
+
+    pb.finish_with_message("All chunks uploaded.");
+
+    let completed_multipart_upload: CompletedMultipartUpload = CompletedMultipartUpload::builder()
+        .set_parts(Some(upload_parts))
+        .build();
+    println!("Completing upload.");
+    aws_client
+        .complete_multipart_upload()
+        .bucket(bucket_name)
+        .key(key)
+        .multipart_upload(completed_multipart_upload)
+        .upload_id(&upload_id)
+        .send()
+        .await
+        .map_err(|err| UploadError::CompleteMultipartUpload {
+            upload_id: upload_id.clone(),
+            source: err.into(),
+        })?;
+    println!("Done uploading file.");
+
+    Ok(())
+}
+
+/// Discovers in-progress multipart uploads for `key`, returning their
+/// `upload_id`s in the order S3 reports them so a caller can pick one to
+/// pass to [`resume_file_upload`].
+pub async fn list_in_progress_uploads(
+    aws_client: &S3Client,
+    bucket_name: &str,
+    key: &str,
+) -> Result<Vec<String>, UploadError> {
+    let mut upload_ids = Vec::new();
+    let mut key_marker = None;
+    let mut upload_id_marker = None;
+
+    loop {
+        let res = aws_client
+            .list_multipart_uploads()
+            .bucket(bucket_name)
+            .prefix(key)
+            .set_key_marker(key_marker.take())
+            .set_upload_id_marker(upload_id_marker.take())
+            .send()
+            .await
+            .map_err(|err| UploadError::ListMultipartUploads(err.into()))?;
+
+        upload_ids.extend(
+            res.uploads()
+                .iter()
+                .filter(|upload| upload.key() == Some(key))
+                .filter_map(|upload| upload.upload_id().map(str::to_string)),
+        );
+
+        if !res.is_truncated().unwrap_or(false) {
+            break;
+        }
+        key_marker = res.next_key_marker().map(str::to_string);
+        upload_id_marker = res.next_upload_id_marker().map(str::to_string);
+    }
+
+    Ok(upload_ids)
+}
+
+/// Resumes a multipart upload that died partway through. Fetches the parts
+/// S3 already has via `list_parts`, advances the progress bar to the bytes
+/// already uploaded, and only sends the chunks still missing before
+/// calling `complete_multipart_upload` — turning a crashed upload into a
+/// cheap continuation instead of a full redo.
+///
+/// `chunk_size` must be the same value the original upload was created
+/// with. The byte offset of each still-missing part is recomputed from it,
+/// so a different value would make the new parts cover the wrong ranges;
+/// this is caught by comparing `chunk_size` against the sizes `list_parts`
+/// reports for the parts already uploaded, returning
+/// [`UploadError::PartSizeMismatch`] instead of completing a corrupt object.
+pub async fn resume_file_upload(
+    aws_client: &S3Client,
+    bucket_name: &str,
+    key: &str,
+    upload_id: &str,
+    chunk_size: u64,
+    max_part_attempts: u32,
+) -> Result<(), UploadError> {
+    let path = Path::new(&key);
+    let file_size = tokio::fs::metadata(path)
+        .await
+        .expect("it exists I swear")
+        .len();
+
+    let (chunk_count, size_of_last_chunk) = chunk_layout(file_size, chunk_size);
+
+    let mut upload_parts: Vec<CompletedPart> = Vec::new();
+    let mut existing_part_sizes: Vec<(i32, u64)> = Vec::new();
+    let mut already_uploaded_bytes: u64 = 0;
+    let mut part_number_marker = None;
+
+    loop {
+        let existing_parts = aws_client
+            .list_parts()
+            .bucket(bucket_name)
+            .key(key)
+            .upload_id(upload_id)
+            .set_part_number_marker(part_number_marker.take())
+            .send()
+            .await
+            .map_err(|err| UploadError::ListParts {
+                upload_id: upload_id.to_string(),
+                source: err.into(),
+            })?;
+
+        upload_parts.extend(existing_parts.parts().iter().map(|part| {
+            CompletedPart::builder()
+                .set_e_tag(part.e_tag().map(str::to_string))
+                .set_part_number(part.part_number())
+                .build()
+        }));
+        existing_part_sizes.extend(existing_parts.parts().iter().filter_map(|part| {
+            Some((part.part_number()?, part.size().unwrap_or(0) as u64))
+        }));
+        already_uploaded_bytes += existing_parts
+            .parts()
+            .iter()
+            .map(|part| part.size().unwrap_or(0) as u64)
+            .sum::<u64>();
+
+        if !existing_parts.is_truncated().unwrap_or(false) {
+            break;
+        }
+        part_number_marker = existing_parts.next_part_number_marker().map(str::to_string);
+    }
+
+    // `chunk_size` must match whatever the original upload was created with: the
+    // offsets for the chunks still missing are recomputed from it below, and if it
+    // disagrees with the part sizes S3 already has, those offsets land on the wrong
+    // byte ranges and complete_multipart_upload would silently assemble a corrupt
+    // object.
+    for (part_number, actual) in &existing_part_sizes {
+        let expected = if *part_number as u64 == chunk_count {
+            size_of_last_chunk
+        } else {
+            chunk_size
+        };
+        if *actual != expected {
+            return Err(UploadError::PartSizeMismatch {
+                upload_id: upload_id.to_string(),
+                part_number: *part_number,
+                expected,
+                actual: *actual,
+            });
+        }
+    }
+
+    let already_uploaded: std::collections::HashSet<i32> = upload_parts
+        .iter()
+        .filter_map(|part| part.part_number())
+        .collect();
+
+    println!(
+        "Resuming upload {}: {} of {} chunks already uploaded.",
+        upload_id,
+        already_uploaded.len(),
+        chunk_count
+    );
+
+    let pb = ProgressBar::new(file_size);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.white/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+        .unwrap()
+        .progress_chars("█  "));
+    pb.set_message(format!("Resuming {} to {}", key, bucket_name));
+    pb.set_position(already_uploaded_bytes);
+
+    for chunk_index in 0..chunk_count {
+        let part_number = (chunk_index as i32) + 1;
+        if already_uploaded.contains(&part_number) {
+            continue;
+        }
+
+        let this_chunk = if chunk_count - 1 == chunk_index {
+            size_of_last_chunk
+        } else {
+            chunk_size
+        };
+        let uploaded = chunk_index * chunk_size;
+        pb.set_message(format!(
+            "Uploading chunk {} of {}.",
+            chunk_index + 1,
+            chunk_count
+        ));
+
+        let part = match upload_part_with_retry(
+            aws_client,
+            bucket_name,
+            key,
+            upload_id,
+            part_number,
+            path,
+            uploaded,
+            this_chunk,
+            max_part_attempts,
+        )
+        .await
+        {
+            Ok(part) => part,
+            Err(err) => {
+                pb.abandon_with_message("Upload failed, aborting.");
+                if let Err(abort_err) = abort_upload(aws_client, bucket_name, key, upload_id).await {
+                    eprintln!("failed to abort orphaned upload {upload_id}: {abort_err}");
+                }
+                return Err(err);
+            }
+        };
+        upload_parts.push(part);
+        pb.set_position(uploaded + this_chunk);
+    }
+    upload_parts.sort_by_key(|part| part.part_number());
+
+    pb.finish_with_message("All chunks uploaded.");
+
+    let completed_multipart_upload: CompletedMultipartUpload = CompletedMultipartUpload::builder()
+        .set_parts(Some(upload_parts))
+        .build();
+    println!("Completing upload.");
+    aws_client
+        .complete_multipart_upload()
+        .bucket(bucket_name)
+        .key(key)
+        .multipart_upload(completed_multipart_upload)
+        .upload_id(upload_id)
+        .send()
+        .await
+        .map_err(|err| UploadError::CompleteMultipartUpload {
+            upload_id: upload_id.to_string(),
+            source: err.into(),
+        })?;
+    println!("Done uploading file.");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod chunk_layout_tests {
+    use super::chunk_layout;
+
+    #[test]
+    fn splits_evenly_when_file_size_is_a_multiple_of_chunk_size() {
+        assert_eq!(chunk_layout(20, 5), (4, 5));
+    }
+
+    #[test]
+    fn last_chunk_absorbs_the_remainder() {
+        assert_eq!(chunk_layout(22, 5), (5, 2));
+    }
+
+    #[test]
+    fn single_chunk_when_file_size_is_under_chunk_size() {
+        assert_eq!(chunk_layout(3, 5), (1, 3));
+    }
+
+    #[test]
+    fn single_full_chunk_when_file_size_equals_chunk_size() {
+        assert_eq!(chunk_layout(5, 5), (1, 5));
+    }
+}